@@ -1,17 +1,24 @@
 use auto_update::{AutoUpdateStatus, AutoUpdater, DismissErrorMessage};
+use diagnostics::ProjectDiagnosticsEditor;
 use editor::Editor;
 use futures::StreamExt;
 use gpui::{
     actions, anyhow,
     elements::*,
     platform::{CursorStyle, MouseButton},
-    AppContext, Entity, ModelHandle, View, ViewContext, ViewHandle,
+    AppContext, Entity, ModelHandle, View, ViewContext, ViewHandle, WeakViewHandle,
 };
 use language::{LanguageRegistry, LanguageServerBinaryStatus};
-use project::{LanguageServerProgress, Project};
+use project::{LanguageServerId, LanguageServerProgress, Project};
 use settings::Settings;
 use smallvec::SmallVec;
-use std::{cmp::Reverse, fmt::Write, sync::Arc};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Write,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use util::ResultExt;
 use workspace::{item::ItemHandle, StatusItemView, Workspace};
 
@@ -19,15 +26,31 @@ actions!(lsp_status, [ShowErrorMessage]);
 
 const DOWNLOAD_ICON: &str = "icons/download_12.svg";
 const WARNING_ICON: &str = "icons/triangle_exclamation_12.svg";
+const CANCEL_ICON: &str = "icons/x_mark_8.svg";
 
 pub enum Event {
     ShowError { lsp_name: Arc<str>, error: String },
 }
 
+fn format_elapsed(duration: Duration) -> String {
+    let total_seconds = duration.as_secs();
+    if total_seconds < 60 {
+        format!("{}s", total_seconds)
+    } else {
+        format!("{}m {}s", total_seconds / 60, total_seconds % 60)
+    }
+}
+
 pub struct ActivityIndicator {
     statuses: Vec<LspStatus>,
     project: ModelHandle<Project>,
     auto_updater: Option<ModelHandle<AutoUpdater>>,
+    cancelled_tokens: HashSet<(LanguageServerId, String)>,
+    lsp_work_popover: Option<ViewHandle<LspWorkPopover>>,
+    diagnostic_summary: project::DiagnosticSummary,
+    pending_diagnostic_checks: HashSet<LanguageServerId>,
+    workspace: WeakViewHandle<Workspace>,
+    progress_percentages: HashMap<(LanguageServerId, String), (Instant, u32)>,
 }
 
 struct LspStatus {
@@ -35,7 +58,15 @@ struct LspStatus {
     status: LanguageServerBinaryStatus,
 }
 
+enum CancelButton {}
+
+struct LspWorkPopover {
+    project: ModelHandle<Project>,
+    activity_indicator: WeakViewHandle<ActivityIndicator>,
+}
+
 struct PendingWork<'a> {
+    language_server_id: LanguageServerId,
     language_server_name: &'a str,
     progress_token: &'a str,
     progress: &'a LanguageServerProgress,
@@ -46,6 +77,7 @@ struct Content {
     icon: Option<&'static str>,
     message: String,
     on_click: Option<Arc<dyn Fn(&mut ActivityIndicator, &mut ViewContext<ActivityIndicator>)>>,
+    cancel: Option<Arc<dyn Fn(&mut ActivityIndicator, &mut ViewContext<ActivityIndicator>)>>,
 }
 
 pub fn init(cx: &mut AppContext) {
@@ -61,6 +93,7 @@ impl ActivityIndicator {
     ) -> ViewHandle<ActivityIndicator> {
         let project = workspace.project().clone();
         let auto_updater = AutoUpdater::get(cx);
+        let workspace_handle = cx.weak_handle();
         let this = cx.add_view(|cx: &mut ViewContext<Self>| {
             let mut status_events = languages.language_server_binary_statuses();
             cx.spawn(|this, mut cx| async move {
@@ -78,16 +111,35 @@ impl ActivityIndicator {
             })
             .detach();
             cx.observe(&project, |_, _, cx| cx.notify()).detach();
+            cx.subscribe(&project, |this, _, event, cx| {
+                this.on_project_event(event, cx)
+            })
+            .detach();
             if let Some(auto_updater) = auto_updater.as_ref() {
                 cx.observe(auto_updater, |_, _, cx| cx.notify()).detach();
             }
             cx.observe_active_labeled_tasks(|_, cx| cx.notify())
                 .detach();
+            cx.spawn(|this, mut cx| async move {
+                loop {
+                    cx.background().timer(Duration::from_secs(1)).await;
+                    if this.update(&mut cx, |_, cx| cx.notify()).is_err() {
+                        break;
+                    }
+                }
+            })
+            .detach();
 
             Self {
                 statuses: Default::default(),
                 project: project.clone(),
                 auto_updater,
+                cancelled_tokens: Default::default(),
+                lsp_work_popover: None,
+                diagnostic_summary: project.read(cx).diagnostic_summary(cx),
+                pending_diagnostic_checks: Default::default(),
+                workspace: workspace_handle,
+                progress_percentages: Default::default(),
             }
         });
         cx.subscribe(&this, move |workspace, _, event, cx| match event {
@@ -142,9 +194,9 @@ impl ActivityIndicator {
     }
 
     fn pending_language_server_work<'a>(
-        &self,
+        &'a self,
         cx: &'a AppContext,
-    ) -> impl Iterator<Item = PendingWork<'a>> {
+    ) -> impl Iterator<Item = PendingWork<'a>> + 'a {
         self.project
             .read(cx)
             .language_server_statuses()
@@ -156,7 +208,13 @@ impl ActivityIndicator {
                     let mut pending_work = status
                         .pending_work
                         .iter()
+                        .filter(|(token, _)| {
+                            !self
+                                .cancelled_tokens
+                                .contains(&(status.id, token.to_string()))
+                        })
                         .map(|(token, progress)| PendingWork {
+                            language_server_id: status.id,
                             language_server_name: status.name.as_str(),
                             progress_token: token.as_str(),
                             progress,
@@ -169,15 +227,97 @@ impl ActivityIndicator {
             .flatten()
     }
 
+    fn is_token_live(project: &Project, (language_server_id, token): &(LanguageServerId, String)) -> bool {
+        project
+            .language_server_statuses()
+            .find(|status| status.id == *language_server_id)
+            .map_or(false, |status| status.pending_work.contains_key(token))
+    }
+
+    /// Tokens only ever enter `cancelled_tokens` optimistically, on click; once the server's
+    /// `WorkDoneProgressEnd` actually retires a token (so it drops out of `pending_work`), there's
+    /// no reason to keep hiding it, and a later server that reuses the same token for unrelated
+    /// work should not be hidden forever.
+    fn prune_cancelled_tokens(&mut self, cx: &mut ViewContext<Self>) {
+        if self.cancelled_tokens.is_empty() {
+            return;
+        }
+        let project = self.project.read(cx);
+        self.cancelled_tokens
+            .retain(|key| Self::is_token_live(project, key));
+    }
+
+    /// Mirrors `prune_cancelled_tokens`: a token only belongs in this cache while its work is
+    /// still pending, so once it's no longer in `pending_work` (ended, or cancelled) drop its
+    /// cached baseline rather than leaking it, or letting a later run that reuses the same token
+    /// inherit a stale percentage.
+    fn prune_progress_percentages(&mut self, cx: &mut ViewContext<Self>) {
+        if self.progress_percentages.is_empty() {
+            return;
+        }
+        let project = self.project.read(cx);
+        self.progress_percentages
+            .retain(|key, _| Self::is_token_live(project, key));
+    }
+
+    fn on_project_event(&mut self, event: &project::Event, cx: &mut ViewContext<Self>) {
+        match event {
+            project::Event::DiskBasedDiagnosticsStarted { language_server_id } => {
+                self.pending_diagnostic_checks.insert(*language_server_id);
+            }
+            project::Event::DiskBasedDiagnosticsFinished { language_server_id } => {
+                self.pending_diagnostic_checks.remove(language_server_id);
+                self.diagnostic_summary = self.project.read(cx).diagnostic_summary(cx);
+            }
+            project::Event::DiagnosticsUpdated { .. } => {
+                self.diagnostic_summary = self.project.read(cx).diagnostic_summary(cx);
+            }
+            _ => {}
+        }
+        cx.notify();
+    }
+
+    fn toggle_lsp_work_popover(&mut self, cx: &mut ViewContext<Self>) {
+        if self.lsp_work_popover.take().is_none() {
+            let project = self.project.clone();
+            let activity_indicator = cx.weak_handle();
+            self.lsp_work_popover = Some(cx.add_view(|_| LspWorkPopover {
+                project,
+                activity_indicator,
+            }));
+        }
+        cx.notify();
+    }
+
+    fn cancel_language_server_work(
+        &mut self,
+        language_server_id: LanguageServerId,
+        token: String,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.cancelled_tokens
+            .insert((language_server_id, token.clone()));
+        self.project.update(cx, |project, cx| {
+            project.cancel_language_server_work(language_server_id, Some(token), cx);
+        });
+        cx.notify();
+    }
+
     fn content_to_render(&mut self, cx: &mut ViewContext<Self>) -> Content {
+        self.prune_cancelled_tokens(cx);
+        self.prune_progress_percentages(cx);
+
         // Show any language server has pending activity.
         let mut pending_work = self.pending_language_server_work(cx);
         if let Some(PendingWork {
+            language_server_id,
             language_server_name,
             progress_token,
             progress,
         }) = pending_work.next()
         {
+            let additional_work_count = pending_work.count();
+
             let mut message = language_server_name.to_string();
 
             message.push_str(": ");
@@ -191,17 +331,63 @@ impl ActivityIndicator {
                 write!(&mut message, " ({}%)", percentage).unwrap();
             }
 
-            let additional_work_count = pending_work.count();
+            let elapsed = Instant::now().saturating_duration_since(progress.started_at);
+            write!(&mut message, " · {}", format_elapsed(elapsed)).unwrap();
+
+            let key = (language_server_id, progress_token.to_string());
+            let advancing = match progress.percentage {
+                Some(percentage) if percentage > 0 => {
+                    let percentage = percentage as u32;
+                    let previous = self
+                        .progress_percentages
+                        .insert(key, (progress.started_at, percentage));
+                    // A previous baseline only counts if it came from this same `Begin` — a
+                    // fresh one (different `started_at`, e.g. a reused token) starts over.
+                    previous.map_or(false, |(started_at, previous_percentage)| {
+                        started_at == progress.started_at && percentage > previous_percentage
+                    })
+                }
+                _ => {
+                    self.progress_percentages.remove(&key);
+                    false
+                }
+            };
+            if advancing {
+                if let Some(percentage) = progress.percentage {
+                    let eta = elapsed
+                        .mul_f32(100.0 / percentage as f32)
+                        .saturating_sub(elapsed);
+                    write!(&mut message, ", ETA {}", format_elapsed(eta)).unwrap();
+                }
+            }
+
             if additional_work_count > 0 {
                 write!(&mut message, " + {} more", additional_work_count).unwrap();
             }
 
+            let cancel: Option<Arc<dyn Fn(&mut Self, &mut ViewContext<Self>)>> =
+                if progress.cancellable {
+                    let progress_token = progress_token.to_string();
+                    Some(Arc::new(move |this: &mut Self, cx: &mut ViewContext<Self>| {
+                        this.cancel_language_server_work(
+                            language_server_id,
+                            progress_token.clone(),
+                            cx,
+                        )
+                    }))
+                } else {
+                    None
+                };
+
             return Content {
                 icon: None,
                 message,
-                on_click: None,
+                on_click: Some(Arc::new(|this, cx| this.toggle_lsp_work_popover(cx))),
+                cancel,
             };
         }
+        drop(pending_work);
+        self.lsp_work_popover = None;
 
         // Show any language server installation info.
         let mut downloading = SmallVec::<[_; 3]>::new();
@@ -231,6 +417,7 @@ impl ActivityIndicator {
                     if downloading.len() > 1 { "s" } else { "" }
                 ),
                 on_click: None,
+                cancel: None,
             };
         } else if !checking_for_update.is_empty() {
             return Content {
@@ -245,6 +432,7 @@ impl ActivityIndicator {
                     }
                 ),
                 on_click: None,
+                cancel: None,
             };
         } else if !failed.is_empty() {
             return Content {
@@ -257,6 +445,35 @@ impl ActivityIndicator {
                 on_click: Some(Arc::new(|this, cx| {
                     this.show_error_message(&Default::default(), cx)
                 })),
+                cancel: None,
+            };
+        }
+
+        // Show a summary of project diagnostics, e.g. from a flycheck/`cargo check` run.
+        if !self.pending_diagnostic_checks.is_empty() {
+            return Content {
+                icon: None,
+                message: "Checking…".to_string(),
+                on_click: None,
+                cancel: None,
+            };
+        }
+
+        if self.diagnostic_summary.error_count > 0 || self.diagnostic_summary.warning_count > 0 {
+            return Content {
+                icon: Some(WARNING_ICON),
+                message: format!(
+                    "✖ {}  ⚠ {}",
+                    self.diagnostic_summary.error_count, self.diagnostic_summary.warning_count
+                ),
+                on_click: Some(Arc::new(|this, cx| {
+                    if let Some(workspace) = this.workspace.upgrade(cx) {
+                        workspace.update(cx, |workspace, cx| {
+                            ProjectDiagnosticsEditor::deploy(workspace, &Default::default(), cx)
+                        });
+                    }
+                })),
+                cancel: None,
             };
         }
 
@@ -267,16 +484,19 @@ impl ActivityIndicator {
                     icon: Some(DOWNLOAD_ICON),
                     message: "Checking for Zed updates…".to_string(),
                     on_click: None,
+                    cancel: None,
                 },
                 AutoUpdateStatus::Downloading => Content {
                     icon: Some(DOWNLOAD_ICON),
                     message: "Downloading Zed update…".to_string(),
                     on_click: None,
+                    cancel: None,
                 },
                 AutoUpdateStatus::Installing => Content {
                     icon: Some(DOWNLOAD_ICON),
                     message: "Installing Zed update…".to_string(),
                     on_click: None,
+                    cancel: None,
                 },
                 AutoUpdateStatus::Updated => Content {
                     icon: None,
@@ -284,6 +504,7 @@ impl ActivityIndicator {
                     on_click: Some(Arc::new(|_, cx| {
                         workspace::restart(&Default::default(), cx)
                     })),
+                    cancel: None,
                 },
                 AutoUpdateStatus::Errored => Content {
                     icon: Some(WARNING_ICON),
@@ -291,6 +512,7 @@ impl ActivityIndicator {
                     on_click: Some(Arc::new(|this, cx| {
                         this.dismiss_error_message(&Default::default(), cx)
                     })),
+                    cancel: None,
                 },
                 AutoUpdateStatus::Idle => Default::default(),
             };
@@ -301,6 +523,7 @@ impl ActivityIndicator {
                 icon: None,
                 message: most_recent_active_task.to_string(),
                 on_click: None,
+                cancel: None,
             };
         }
 
@@ -322,6 +545,7 @@ impl View for ActivityIndicator {
             icon,
             message,
             on_click,
+            cancel,
         } = self.content_to_render(cx);
 
         let mut element = MouseEventHandler::<Self, _>::new(0, cx, |state, cx| {
@@ -352,6 +576,19 @@ impl View for ActivityIndicator {
                         .with_soft_wrap(false)
                         .aligned(),
                 )
+                .with_children(cancel.clone().map(|cancel| {
+                    MouseEventHandler::<CancelButton, _>::new(0, cx, |_, _| {
+                        Svg::new(CANCEL_ICON)
+                            .with_color(style.icon_color)
+                            .constrained()
+                            .with_width(style.icon_width)
+                    })
+                    .with_cursor_style(CursorStyle::PointingHand)
+                    .on_click(MouseButton::Left, move |_, this, cx| cancel(this, cx))
+                    .contained()
+                    .with_margin_left(style.icon_spacing)
+                    .aligned()
+                }))
                 .constrained()
                 .with_height(style.height)
                 .contained()
@@ -365,10 +602,87 @@ impl View for ActivityIndicator {
                 .on_click(MouseButton::Left, move |_, this, cx| on_click(this, cx));
         }
 
-        element.into_any()
+        Stack::new()
+            .with_child(element)
+            .with_children(self.lsp_work_popover.as_ref().map(|popover| {
+                Overlay::new(ChildView::new(popover, cx).contained())
+                    .with_fit_mode(OverlayFitMode::SwitchAnchor)
+                    .with_anchor_corner(AnchorCorner::BottomLeft)
+                    .with_z_index(999)
+                    .aligned()
+                    .bottom()
+                    .left()
+            }))
+            .into_any()
     }
 }
 
 impl StatusItemView for ActivityIndicator {
     fn set_active_pane_item(&mut self, _: Option<&dyn ItemHandle>, _: &mut ViewContext<Self>) {}
 }
+
+impl Entity for LspWorkPopover {
+    type Event = ();
+}
+
+impl View for LspWorkPopover {
+    fn ui_name() -> &'static str {
+        "LspWorkPopover"
+    }
+
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> AnyElement<Self> {
+        let theme = &cx.global::<Settings>().theme.workspace.status_bar.lsp_status;
+
+        let cancelled_tokens = self
+            .activity_indicator
+            .upgrade(cx)
+            .map(|indicator| indicator.read(cx).cancelled_tokens.clone())
+            .unwrap_or_default();
+
+        // Group by language server name rather than by `LanguageServerId`, so that
+        // multiple server instances sharing a name (e.g. restarts) don't show up as
+        // separate headers.
+        let mut groups: BTreeMap<String, Vec<(String, LanguageServerProgress)>> = BTreeMap::new();
+        for status in self.project.read(cx).language_server_statuses() {
+            if status.pending_work.is_empty() {
+                continue;
+            }
+            let rows = groups.entry(status.name.to_string()).or_default();
+            rows.extend(status.pending_work.iter().filter_map(|(token, progress)| {
+                if cancelled_tokens.contains(&(status.id, token.clone())) {
+                    None
+                } else {
+                    Some((token.clone(), progress.clone()))
+                }
+            }));
+        }
+        groups.retain(|_, rows| !rows.is_empty());
+        for rows in groups.values_mut() {
+            rows.sort_by_key(|(_, progress)| Reverse(progress.last_update_at));
+        }
+
+        Flex::column()
+            .with_children(groups.into_iter().map(|(name, rows)| {
+                Flex::column()
+                    .with_child(Text::new(name, theme.message.clone()))
+                    .with_children(rows.into_iter().map(|(token, progress)| {
+                        let mut row = format!("[{}]", token);
+                        if let Some(message) = progress.message.as_ref() {
+                            write!(&mut row, " {}", message).unwrap();
+                        }
+                        if let Some(percentage) = progress.percentage {
+                            write!(&mut row, " ({}%)", percentage).unwrap();
+                        }
+                        Text::new(row, theme.message.clone())
+                            .contained()
+                            .with_margin_left(theme.icon_spacing)
+                    }))
+                    .into_any()
+            }))
+            .contained()
+            .with_style(theme.container)
+            .constrained()
+            .with_max_width(400.)
+            .into_any()
+    }
+}
@@ -0,0 +1,165 @@
+use collections::{BTreeMap, HashMap};
+use gpui::{AppContext, Entity, ModelContext};
+use lsp::LanguageServer;
+use std::{path::Path, sync::Arc, time::Instant};
+
+pub use lsp::LanguageServerId;
+
+pub enum Event {
+    DiskBasedDiagnosticsStarted {
+        language_server_id: LanguageServerId,
+    },
+    DiskBasedDiagnosticsFinished {
+        language_server_id: LanguageServerId,
+    },
+    DiagnosticsUpdated {
+        language_server_id: LanguageServerId,
+        path: Arc<Path>,
+    },
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DiagnosticSummary {
+    pub error_count: usize,
+    pub warning_count: usize,
+}
+
+impl DiagnosticSummary {
+    fn add(&mut self, other: &DiagnosticSummary) {
+        self.error_count += other.error_count;
+        self.warning_count += other.warning_count;
+    }
+}
+
+/// Tracks an in-flight `$/progress` stream reported by a language server, from the
+/// `WorkDoneProgressBegin` that created its token through to the `WorkDoneProgressEnd`
+/// that retires it.
+#[derive(Clone, Debug)]
+pub struct LanguageServerProgress {
+    pub message: Option<String>,
+    pub percentage: Option<usize>,
+    /// Whether the server advertised this token as cancellable via `window/workDoneProgress/cancel`.
+    pub cancellable: bool,
+    /// When the `WorkDoneProgressBegin` for this token was received.
+    pub started_at: Instant,
+    pub last_update_at: Instant,
+}
+
+pub struct LanguageServerStatus {
+    pub id: LanguageServerId,
+    pub name: Arc<str>,
+    pub pending_work: BTreeMap<String, LanguageServerProgress>,
+}
+
+#[derive(Default)]
+struct ProjectDiagnostics {
+    summaries: HashMap<Arc<Path>, DiagnosticSummary>,
+}
+
+pub struct Project {
+    language_servers: HashMap<LanguageServerId, Arc<LanguageServer>>,
+    language_server_statuses: BTreeMap<LanguageServerId, LanguageServerStatus>,
+    diagnostics: ProjectDiagnostics,
+}
+
+impl Entity for Project {
+    type Event = Event;
+}
+
+impl Project {
+    pub fn language_server_statuses(&self) -> impl Iterator<Item = &LanguageServerStatus> {
+        self.language_server_statuses.values()
+    }
+
+    pub fn diagnostic_summary(&self, _cx: &AppContext) -> DiagnosticSummary {
+        let mut summary = DiagnosticSummary::default();
+        for path_summary in self.diagnostics.summaries.values() {
+            summary.add(path_summary);
+        }
+        summary
+    }
+
+    /// Handles an incoming LSP `$/progress` notification, maintaining the begin/report/end
+    /// lifecycle of `pending_work` and notifying subscribers so `ActivityIndicator` (and anyone
+    /// else watching `language_server_statuses`) can reflect the change.
+    pub fn on_lsp_progress(
+        &mut self,
+        progress: lsp::ProgressParams,
+        language_server_id: LanguageServerId,
+        cx: &mut ModelContext<Self>,
+    ) {
+        let token = match progress.token {
+            lsp::NumberOrString::String(token) => token,
+            lsp::NumberOrString::Number(token) => token.to_string(),
+        };
+        let lsp::ProgressParamsValue::WorkDone(progress) = progress.value;
+
+        let Some(status) = self.language_server_statuses.get_mut(&language_server_id) else {
+            return;
+        };
+
+        match progress {
+            lsp::WorkDoneProgress::Begin(report) => {
+                let now = Instant::now();
+                status.pending_work.insert(
+                    token,
+                    LanguageServerProgress {
+                        message: report.message,
+                        percentage: report.percentage.map(|p| p as usize),
+                        cancellable: report.cancellable.unwrap_or(false),
+                        started_at: now,
+                        last_update_at: now,
+                    },
+                );
+            }
+            lsp::WorkDoneProgress::Report(report) => {
+                if let Some(progress) = status.pending_work.get_mut(&token) {
+                    if report.message.is_some() {
+                        progress.message = report.message;
+                    }
+                    if report.percentage.is_some() {
+                        progress.percentage = report.percentage.map(|p| p as usize);
+                    }
+                    if let Some(cancellable) = report.cancellable {
+                        progress.cancellable = cancellable;
+                    }
+                    progress.last_update_at = Instant::now();
+                }
+            }
+            lsp::WorkDoneProgress::End(_) => {
+                status.pending_work.remove(&token);
+            }
+        }
+
+        cx.notify();
+    }
+
+    /// Sends `window/workDoneProgress/cancel` for `token` (or for every pending token of this
+    /// server, if `token` is `None`). Only tokens we've actually seen a `WorkDoneProgressBegin`
+    /// for are cancelled, since `pending_work` only ever contains live, un-ended tokens.
+    pub fn cancel_language_server_work(
+        &mut self,
+        server_id: LanguageServerId,
+        token: Option<String>,
+        cx: &mut ModelContext<Self>,
+    ) {
+        if let Some(status) = self.language_server_statuses.get(&server_id) {
+            let mut tokens = token
+                .map(|token| vec![token])
+                .unwrap_or_else(|| status.pending_work.keys().cloned().collect());
+            tokens.retain(|token| status.pending_work.contains_key(token));
+            if let Some(server) = self.language_servers.get(&server_id) {
+                for token in tokens {
+                    server
+                        .notify::<lsp::notification::WorkDoneProgressCancel>(
+                            lsp::WorkDoneProgressCancelParams {
+                                token: lsp::NumberOrString::String(token),
+                            },
+                        )
+                        .ok();
+                }
+            }
+        }
+        cx.notify();
+    }
+}